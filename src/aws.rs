@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::str::FromStr;
 
 use log::{debug, warn};
 use rusoto_core::credential::AwsCredentials;
@@ -8,6 +9,41 @@ use vault::{self, Client};
 
 use crate::Error;
 
+/// `client.authentication.k8s.io` API version to emit the `ExecCredential`
+/// as. kubectl/kubelet only cache the credential (honouring
+/// `status.expirationTimestamp`) from `v1beta1` onwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecCredentialVersion {
+    V1Alpha1,
+    V1Beta1,
+    V1,
+}
+
+pub static EXEC_CREDENTIAL_VERSION_VARIANTS: &[&str] = &["v1alpha1", "v1beta1", "v1"];
+
+impl ExecCredentialVersion {
+    fn api_version(self) -> &'static str {
+        match self {
+            ExecCredentialVersion::V1Alpha1 => "client.authentication.k8s.io/v1alpha1",
+            ExecCredentialVersion::V1Beta1 => "client.authentication.k8s.io/v1beta1",
+            ExecCredentialVersion::V1 => "client.authentication.k8s.io/v1",
+        }
+    }
+}
+
+impl FromStr for ExecCredentialVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "v1alpha1" => Ok(ExecCredentialVersion::V1Alpha1),
+            "v1beta1" => Ok(ExecCredentialVersion::V1Beta1),
+            "v1" => Ok(ExecCredentialVersion::V1),
+            _ => Err(Error::InvalidCredentialType),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct EksCredential {
     pub kind: &'static str,
@@ -20,6 +56,29 @@ pub struct EksCredential {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct EksCredentialStatus {
     pub token: String,
+    #[serde(
+        rename = "expirationTimestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expiration_timestamp: Option<String>,
+}
+
+/// AWS SDK/CLI `credential_process` envelope, as consumed by a
+/// `credential_process = ...` line in an AWS profile.
+///
+/// See <https://docs.aws.amazon.com/cli/latest/userguide/cli-configure-sourcing-external.html>
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct AwsCredentialProcess {
+    #[serde(rename = "Version")]
+    pub version: u8,
+    #[serde(rename = "AccessKeyId")]
+    pub access_key_id: String,
+    #[serde(rename = "SecretAccessKey")]
+    pub secret_access_key: String,
+    #[serde(rename = "SessionToken", skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
+    #[serde(rename = "Expiration", skip_serializing_if = "Option::is_none")]
+    pub expiration: Option<String>,
 }
 
 pub fn read_aws_credentials<S: AsRef<str>>(
@@ -95,11 +154,61 @@ pub fn generate_presigned_url(
     ))
 }
 
+/// Exchanges Vault-issued AWS credentials for an ECR authorization token,
+/// returning the decoded `(username, password)` Docker expects as the
+/// registry `Secret`.
+pub async fn get_ecr_authorization_token(
+    credentials: &AwsCredentials,
+    region: Option<&str>,
+) -> Result<(String, String), Error> {
+    use rusoto_ecr::Ecr;
+
+    let region: rusoto_core::Region = match region {
+        Some(r) => r.parse()?,
+        None => rusoto_core::Region::default(),
+    };
+    let provider = rusoto_core::credential::StaticProvider::from(credentials.clone());
+    let dispatcher = rusoto_core::HttpClient::new().map_err(|e| Error::EcrError(e.to_string()))?;
+    let client = rusoto_ecr::EcrClient::new_with(dispatcher, provider, region);
+
+    let response = client
+        .get_authorization_token(rusoto_ecr::GetAuthorizationTokenRequest::default())
+        .await
+        .map_err(|e| Error::EcrError(e.to_string()))?;
+
+    let token = response
+        .authorization_data
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .and_then(|data| data.authorization_token)
+        .ok_or(Error::MissingEcrAuthorizationData)?;
+
+    let decoded = String::from_utf8(base64::decode(&token)?)?;
+    let (username, password) = decoded
+        .split_once(':')
+        .ok_or(Error::MissingEcrAuthorizationData)?;
+    Ok((username.to_string(), password.to_string()))
+}
+
+pub fn get_credential_process_output(credentials: &AwsCredentials) -> AwsCredentialProcess {
+    AwsCredentialProcess {
+        version: 1,
+        access_key_id: credentials.aws_access_key_id().to_string(),
+        secret_access_key: credentials.aws_secret_access_key().to_string(),
+        session_token: credentials.token().clone(),
+        expiration: credentials
+            .expires_at()
+            .map(|expiry| expiry.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+    }
+}
+
 pub fn get_eks_token(
     credentials: &AwsCredentials,
     cluster: &str,
     region: Option<&str>,
     expires_in: Option<&str>,
+    exec_credential_version: ExecCredentialVersion,
 ) -> Result<EksCredential, Error> {
     let url = generate_presigned_url(credentials, cluster, region, expires_in)?;
     debug!("Generated AWS Pre-signed URL: {}", url);
@@ -107,11 +216,18 @@ pub fn get_eks_token(
     let mut token = "k8s-aws-v1.".to_string();
     base64::encode_config_buf(&url, base64::URL_SAFE_NO_PAD, &mut token);
 
+    let expiration_timestamp = credentials
+        .expires_at()
+        .map(|expiry| expiry.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+
     Ok(EksCredential {
         kind: "ExecCredential",
-        api_version: "client.authentication.k8s.io/v1alpha1",
+        api_version: exec_credential_version.api_version(),
         spec: Default::default(),
-        status: EksCredentialStatus { token },
+        status: EksCredentialStatus {
+            token,
+            expiration_timestamp,
+        },
     })
 }
 
@@ -148,6 +264,20 @@ mod tests {
     #[test]
     fn can_create_aws_token() {
         let aws_credentials = aws_credentials();
-        let _ = get_eks_token(&aws_credentials, "test", None, None).unwrap();
+        let _ = get_eks_token(
+            &aws_credentials,
+            "test",
+            None,
+            None,
+            ExecCredentialVersion::V1Beta1,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn can_create_credential_process_output() {
+        let aws_credentials = aws_credentials();
+        let output = get_credential_process_output(&aws_credentials);
+        assert_eq!(output.version, 1);
     }
 }
@@ -0,0 +1,137 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CacheEntry {
+    expires_at: DateTime<Utc>,
+    output: String,
+}
+
+/// Default cache directory, mirroring the `~/.vault-token` convention used
+/// for the Vault CLI token helper, i.e. `~/.vault-k8s-helper/cache`.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|mut p| {
+        p.push(".vault-k8s-helper");
+        p.push("cache");
+        p
+    })
+}
+
+fn cache_key(credential_type: &str, identifier: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    credential_type.hash(&mut hasher);
+    identifier.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn cache_file(cache_dir: &Path, credential_type: &str, identifier: &str) -> PathBuf {
+    cache_dir.join(format!("{}.json", cache_key(credential_type, identifier)))
+}
+
+/// Returns the cached credential output for `credential_type`/`identifier`
+/// if a cache file exists and is not within `safety_margin_secs` seconds of
+/// expiring.
+pub fn read_cached(
+    cache_dir: &Path,
+    credential_type: &str,
+    identifier: &str,
+    safety_margin_secs: i64,
+) -> Option<String> {
+    let path = cache_file(cache_dir, credential_type, identifier);
+    let contents = std::fs::read(&path).ok()?;
+    let entry: CacheEntry = serde_json::from_slice(&contents).ok()?;
+    let safe_until = entry.expires_at - Duration::seconds(safety_margin_secs);
+    if Utc::now() < safe_until {
+        debug!("Using cached credential from {}", path.display());
+        Some(entry.output)
+    } else {
+        None
+    }
+}
+
+/// Persists `output` to the cache, keyed by `credential_type`/`identifier`,
+/// so it can be reused until shortly before `expires_at`.
+pub fn write_cache(
+    cache_dir: &Path,
+    credential_type: &str,
+    identifier: &str,
+    output: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<(), Error> {
+    std::fs::create_dir_all(cache_dir)?;
+    let path = cache_file(cache_dir, credential_type, identifier);
+    let entry = CacheEntry {
+        expires_at,
+        output: output.to_string(),
+    };
+    let contents = serde_json::to_vec(&entry)?;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    std::io::Write::write_all(&mut file, &contents)?;
+    debug!("Wrote cached credential to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_a_fresh_credential() {
+        let dir = std::env::temp_dir().join(format!(
+            "vault-k8s-helper-test-{}",
+            cache_key("eks", "test")
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        write_cache(
+            &dir,
+            "eks",
+            "aws/creds/role",
+            "token-output",
+            Utc::now() + Duration::minutes(5),
+        )
+        .unwrap();
+        assert_eq!(
+            read_cached(&dir, "eks", "aws/creds/role", 60),
+            Some("token-output".to_string())
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn treats_near_expiry_credential_as_stale() {
+        let dir = std::env::temp_dir().join(format!(
+            "vault-k8s-helper-test-{}",
+            cache_key("eks", "expiring")
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        write_cache(
+            &dir,
+            "eks",
+            "aws/creds/role",
+            "token-output",
+            Utc::now() + Duration::seconds(5),
+        )
+        .unwrap();
+        assert_eq!(read_cached(&dir, "eks", "aws/creds/role", 60), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
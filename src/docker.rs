@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use vault::Client;
+
+use crate::{aws, gcp, Error};
+
+/// Credential returned to Docker for the `get` command of the
+/// `docker-credential-helper` protocol.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct DockerCredential {
+    #[serde(rename = "ServerURL")]
+    pub server_url: String,
+    #[serde(rename = "Username")]
+    pub username: String,
+    #[serde(rename = "Secret")]
+    pub secret: String,
+}
+
+/// Which Vault-backed credential type backs a registry entry in
+/// `--registry-map`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryCredentialType {
+    Aws,
+    Gcp,
+}
+
+impl FromStr for RegistryCredentialType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "aws" => Ok(RegistryCredentialType::Aws),
+            "gcp" => Ok(RegistryCredentialType::Gcp),
+            _ => Err(Error::InvalidCredentialType),
+        }
+    }
+}
+
+pub struct RegistryMapping {
+    pub credential_type: RegistryCredentialType,
+    pub path: String,
+}
+
+/// Parses `--registry-map` entries of the form
+/// `<hostname>=<aws|gcp>:<vault-path>`.
+pub fn parse_registry_map<'a, I: Iterator<Item = &'a str>>(
+    entries: I,
+) -> Result<HashMap<String, RegistryMapping>, Error> {
+    entries
+        .map(|entry| {
+            let (hostname, rest) = entry.split_once('=').ok_or(Error::InvalidRegistryMap)?;
+            let (credential_type, path) = rest.split_once(':').ok_or(Error::InvalidRegistryMap)?;
+            Ok((
+                hostname.to_string(),
+                RegistryMapping {
+                    credential_type: credential_type.parse()?,
+                    path: path.to_string(),
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Reads the registry hostname Docker passes on stdin for the `get`
+/// command.
+pub fn read_hostname_from_stdin() -> Result<String, Error> {
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+/// Resolves a registry hostname to a Vault-backed credential.
+pub async fn get(
+    client: &Client,
+    mapping: &RegistryMapping,
+    hostname: &str,
+) -> Result<DockerCredential, Error> {
+    match mapping.credential_type {
+        RegistryCredentialType::Aws => {
+            let request = vault::secrets::aws::CredentialsRequest::default();
+            let credentials = aws::read_aws_credentials(client, &mapping.path, &request).await?;
+            let (username, secret) = aws::get_ecr_authorization_token(&credentials, None).await?;
+            Ok(DockerCredential {
+                server_url: hostname.to_string(),
+                username,
+                secret,
+            })
+        }
+        RegistryCredentialType::Gcp => {
+            let token = gcp::read_gcp_access_token(client, &mapping.path).await?;
+            Ok(DockerCredential {
+                server_url: hostname.to_string(),
+                username: "oauth2accesstoken".to_string(),
+                secret: token.token,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_valid_registry_map_entries() {
+        let map =
+            parse_registry_map(["registry.example.com=aws:aws/creds/role"].into_iter()).unwrap();
+
+        let mapping = &map["registry.example.com"];
+        assert_eq!(mapping.credential_type, RegistryCredentialType::Aws);
+        assert_eq!(mapping.path, "aws/creds/role");
+    }
+
+    #[test]
+    fn rejects_malformed_registry_map_entries() {
+        assert!(matches!(
+            parse_registry_map(["registry.example.com"].into_iter()),
+            Err(Error::InvalidRegistryMap)
+        ));
+        assert!(matches!(
+            parse_registry_map(["registry.example.com=aws"].into_iter()),
+            Err(Error::InvalidRegistryMap)
+        ));
+    }
+}
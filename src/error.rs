@@ -36,6 +36,28 @@ pub enum Error {
     InvalidAwsRegion(#[cause] rusoto_core::region::ParseRegionError),
     #[fail(display = "GCP Authentication error: {}", _0)]
     GCPAuthError(gcp_auth::Error),
+    #[fail(display = "No subject token source provided for GCP workload identity federation")]
+    MissingSubjectToken,
+    #[fail(display = "Error decoding base64 data: {}", _0)]
+    Base64DecodeError(#[cause] base64::DecodeError),
+    #[fail(display = "Error calling the ECR API: {}", _0)]
+    EcrError(String),
+    #[fail(display = "ECR response did not contain an authorization token")]
+    MissingEcrAuthorizationData,
+    #[fail(display = "Invalid `--registry-map` entry, expected `<hostname>=<type>:<path>`")]
+    InvalidRegistryMap,
+    #[fail(display = "No registry mapping configured for hostname: {}", _0)]
+    UnknownRegistry(String),
+    #[fail(display = "Invalid `--refresh-margin`, expected a number between 0.0 and 1.0")]
+    InvalidRefreshMargin,
+    #[fail(display = "Invalid `--cache-safety-margin`, expected a number of seconds")]
+    InvalidCacheSafetyMargin,
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(error: base64::DecodeError) -> Self {
+        Error::Base64DecodeError(error)
+    }
 }
 
 impl From<reqwest::Error> for Error {
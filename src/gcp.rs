@@ -1,12 +1,18 @@
 use std::fmt;
 
 use chrono::{DateTime, Duration, NaiveDateTime, SecondsFormat, Utc};
+use log::debug;
 use serde::de::{self, Deserializer, Visitor};
 use serde::{Deserialize, Serialize};
 use vault::{self, Client, Vault};
 
 use crate::Error;
 
+/// GKE authenticates via the client-go GCP auth provider's own token-cache
+/// format (`token`/`token_expiry`), not an ExecCredential, so there is no
+/// `apiVersion`/`kind` wrapper here and `--exec-credential-version` does not
+/// apply to this path; `expiry` already gives kubectl everything it needs to
+/// cache the token until shortly before it expires.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct GcpAccessToken {
     #[serde(deserialize_with = "timestamp_to_iso")]
@@ -78,6 +84,105 @@ pub async fn read_gcp_access_token<S: AsRef<str>>(
     Ok(data)
 }
 
+/// Subject token type for an OIDC-flavoured external account, e.g. a
+/// Kubernetes projected service account token.
+pub const SUBJECT_TOKEN_TYPE_JWT: &str = "urn:ietf:params:oauth:token-type:jwt";
+
+const STS_TOKEN_ENDPOINT: &str = "https://sts.googleapis.com/v1/token";
+
+/// Configuration for exchanging an external (non-GCP) subject token for a
+/// GCP access token via workload identity federation, optionally
+/// impersonating a target service account.
+pub struct ExternalAccountConfig<'a> {
+    pub audience: &'a str,
+    pub subject_token: &'a str,
+    pub subject_token_type: &'a str,
+    pub scopes: Vec<&'a str>,
+    pub service_account_impersonation_url: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StsTokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImpersonationResponse {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    #[serde(rename = "expireTime")]
+    expire_time: DateTime<Utc>,
+}
+
+/// Fetches a subject token from a URL, e.g. a GKE/EKS metadata endpoint
+/// serving a projected service account token.
+pub async fn fetch_subject_token(url: &str) -> Result<String, Error> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    Ok(response.text().await?)
+}
+
+/// Exchanges an external-account subject token for a GCP access token at
+/// the STS token endpoint, then impersonates the configured service
+/// account if one is set.
+pub async fn read_workload_identity_token(
+    config: &ExternalAccountConfig<'_>,
+) -> Result<GcpAccessToken, Error> {
+    let client = reqwest::Client::new();
+    let scope = config.scopes.join(" ");
+    let params = [
+        ("audience", config.audience),
+        (
+            "grant_type",
+            "urn:ietf:params:oauth:grant-type:token-exchange",
+        ),
+        (
+            "requested_token_type",
+            "urn:ietf:params:oauth:token-type:access_token",
+        ),
+        ("subject_token_type", config.subject_token_type),
+        ("subject_token", config.subject_token),
+        ("scope", scope.as_str()),
+    ];
+    debug!("Exchanging subject token at {}", STS_TOKEN_ENDPOINT);
+    let federated_token: StsTokenResponse = client
+        .post(STS_TOKEN_ENDPOINT)
+        .form(&params)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    if let Some(impersonation_url) = config.service_account_impersonation_url {
+        debug!("Impersonating service account at {}", impersonation_url);
+        let body = serde_json::json!({ "scope": config.scopes });
+        let impersonated: ImpersonationResponse = client
+            .post(impersonation_url)
+            .bearer_auth(&federated_token.access_token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        return Ok(GcpAccessToken {
+            token: impersonated.access_token,
+            token_ttl: (impersonated.expire_time - Utc::now()).num_seconds().max(0) as u64,
+            expiry: impersonated
+                .expire_time
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+        });
+    }
+
+    let expiry = Utc::now() + Duration::seconds(federated_token.expires_in as i64);
+    Ok(GcpAccessToken {
+        token: federated_token.access_token,
+        token_ttl: federated_token.expires_in,
+        expiry: expiry.to_rfc3339_opts(SecondsFormat::Secs, true),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
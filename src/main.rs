@@ -3,14 +3,21 @@
 mod error;
 pub use error::Error;
 mod aws;
+mod cache;
+mod docker;
 mod gcp;
+mod serve;
 
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::Write;
+use std::path::PathBuf;
 use std::str::FromStr;
 
-use clap::{crate_authors, crate_name, crate_version, App, AppSettings, Arg, ArgMatches};
+use chrono::{DateTime, Utc};
+use clap::{
+    crate_authors, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand,
+};
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use vault::{self, Client};
@@ -20,9 +27,10 @@ enum CredentialType {
     Gke,
     Eks,
     Gcp,
+    AwsProcess,
 }
 
-static CRED_VARIANTS: &[&str] = &["gke", "eks", "gcp"];
+static CRED_VARIANTS: &[&str] = &["gke", "eks", "gcp", "aws-process"];
 
 impl FromStr for CredentialType {
     type Err = Error;
@@ -32,6 +40,7 @@ impl FromStr for CredentialType {
             "gke" => Ok(CredentialType::Gke),
             "eks" => Ok(CredentialType::Eks),
             "gcp" => Ok(CredentialType::Gcp),
+            "aws-process" => Ok(CredentialType::AwsProcess),
             _ => Err(Error::InvalidCredentialType),
         }
     }
@@ -41,50 +50,202 @@ fn read_file<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<u8>, Error> {
     Ok(std::fs::read(&path)?)
 }
 
+fn vault_connection_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("vault_address")
+            .help("Vault Address")
+            .long("--vault-address")
+            .long_help(
+                "Specifies the Vault Address to connect to. \
+                 Include the scheme and port. \
+                 Can be provided by the `VAULT_ADDR` environment variable as well",
+            )
+            .takes_value(true),
+        Arg::with_name("vault_token")
+            .help("Vault Token")
+            .long("--vault-token")
+            .long_help(
+                "Specifies the Vault token to use with Vault. \
+                 Can be provided by the `VAULT_TOKEN` environment variable as well",
+            )
+            .takes_value(true),
+        Arg::with_name("vault_token_file")
+            .help("Vault Token File")
+            .long("--vault-token-file")
+            .long_help("Specifies a path to Vault token to read from and use with Vault.")
+            .takes_value(true)
+            .conflicts_with("vault_token"),
+        Arg::with_name("vault_ca_cert")
+            .help("Vault CA Certificate")
+            .long("--vault-ca-cert")
+            .long_help(
+                "Specifies a path to the PEM encoded CA Certificate for Vault. \
+                 Can be provided by the `VAULT_CACERT` environment variable as well",
+            )
+            .takes_value(true),
+    ]
+}
+
 fn make_parser<'a, 'b>() -> App<'a, 'b> {
     App::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!())
         .global_setting(AppSettings::NextLineHelp)
+        .setting(AppSettings::SubcommandsNegateReqs)
         .about("Read access tokens from Vault to authenticate with Kubernetes")
-        .arg(
-            Arg::with_name("vault_address")
-                .help("Vault Address")
-                .long("--vault-address")
-                .long_help(
-                    "Specifies the Vault Address to connect to. \
-                     Include the scheme and port. \
-                     Can be provided by the `VAULT_ADDR` environment variable as well",
+        .args(&vault_connection_args())
+        .subcommand(
+            SubCommand::with_name("docker")
+                .about("Docker credential helper backed by Vault")
+                .args(&vault_connection_args())
+                .arg(
+                    Arg::with_name("registry_map")
+                        .long("--registry-map")
+                        .help("Registry to Vault Credential Mapping")
+                        .long_help(
+                            "Maps a registry hostname to a Vault-backed credential, in the form \
+                             `<hostname>=<aws|gcp>:<vault-path>`. May be given multiple times",
+                        )
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
                 )
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("vault_token")
-                .help("Vault Token")
-                .long("--vault-token")
-                .long_help(
-                    "Specifies the Vault token to use with Vault. \
-                     Can be provided by the `VAULT_TOKEN` environment variable as well",
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Returns credentials for the registry hostname given on stdin"),
                 )
-                .takes_value(true),
-        )
-        .arg(
-            Arg::with_name("vault_token_file")
-                .help("Vault Token File")
-                .long("--vault-token-file")
-                .long_help("Specifies a path to Vault token to read from and use with Vault.")
-                .takes_value(true)
-                .conflicts_with("vault_token"),
+                .subcommand(
+                    SubCommand::with_name("store")
+                        .about("No-op: credentials are always sourced fresh from Vault on `get`"),
+                )
+                .subcommand(
+                    SubCommand::with_name("erase")
+                        .about("No-op: credentials are always sourced fresh from Vault on `get`"),
+                ),
         )
-        .arg(
-            Arg::with_name("vault_ca_cert")
-                .help("Vault CA Certificate")
-                .long("--vault-ca-cert")
-                .long_help(
-                    "Specifies a path to the PEM encoded CA Certificate for Vault. \
-                     Can be provided by the `VAULT_CACERT` environment variable as well",
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about(
+                    "Runs a long-lived daemon that refreshes the credential before expiry and \
+                     serves it over a Unix domain socket",
                 )
-                .takes_value(true),
+                .args(&vault_connection_args())
+                .arg(
+                    Arg::with_name("socket_path")
+                        .long("--socket-path")
+                        .help("Unix Domain Socket Path")
+                        .long_help(
+                            "Path of the Unix domain socket to serve the credential on. \
+                             Defaults to `~/.vault-k8s-helper/serve.sock`",
+                        )
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("refresh_margin")
+                        .long("--refresh-margin")
+                        .help("Refresh Margin")
+                        .long_help(
+                            "Fraction (0.0-1.0) of the credential's TTL after which it is \
+                             proactively refreshed",
+                        )
+                        .takes_value(true)
+                        .default_value("0.8"),
+                )
+                .arg(
+                    Arg::with_name("type")
+                        .help("Credentials Type")
+                        .long_help("Type of credentials to read")
+                        .takes_value(true)
+                        .possible_values(CRED_VARIANTS)
+                        .index(1)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .help("Vault Path")
+                        .long_help("Path to read from Vault")
+                        .takes_value(true)
+                        .index(2)
+                        .required_ifs(&[("type", "gke"), ("type", "eks"), ("type", "aws-process")]),
+                )
+                .arg(
+                    Arg::with_name("eks_role_arn")
+                        .long("--eks-role-arn")
+                        .help("AWS IAM Role ARN")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("eks_ttl")
+                        .long("--eks-ttl")
+                        .help("STS Token TTL")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("eks_expiry")
+                        .long("--eks-expiry")
+                        .help("EKS Token Expiry")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("eks_cluster")
+                        .long("--eks-cluster")
+                        .help("EKS Cluster Name")
+                        .takes_value(true)
+                        .required_if("type", "eks"),
+                )
+                .arg(
+                    Arg::with_name("eks_region")
+                        .long("--eks-region")
+                        .help("AWS Region")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("exec_credential_version")
+                        .long("--exec-credential-version")
+                        .help("ExecCredential API Version (eks only)")
+                        .takes_value(true)
+                        .possible_values(aws::EXEC_CREDENTIAL_VERSION_VARIANTS)
+                        .default_value("v1beta1"),
+                )
+                .arg(
+                    Arg::with_name("gcp_workload_audience")
+                        .long("--gcp-workload-audience")
+                        .help("GCP Workload Identity Pool Audience")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("gcp_subject_token_file")
+                        .long("--gcp-subject-token-file")
+                        .help("GCP Subject Token File")
+                        .takes_value(true)
+                        .conflicts_with("gcp_subject_token_url"),
+                )
+                .arg(
+                    Arg::with_name("gcp_subject_token_url")
+                        .long("--gcp-subject-token-url")
+                        .help("GCP Subject Token URL")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("gcp_subject_token_type")
+                        .long("--gcp-subject-token-type")
+                        .help("GCP Subject Token Type")
+                        .takes_value(true)
+                        .default_value(gcp::SUBJECT_TOKEN_TYPE_JWT),
+                )
+                .arg(
+                    Arg::with_name("gcp_service_account_impersonation_url")
+                        .long("--gcp-service-account-impersonation-url")
+                        .help("GCP Service Account Impersonation URL")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("gcp_scopes")
+                        .long("--gcp-scopes")
+                        .help("GCP OAuth Scopes")
+                        .takes_value(true)
+                        .default_value("https://www.googleapis.com/auth/cloud-platform"),
+                ),
         )
         .arg(
             Arg::with_name("output")
@@ -137,6 +298,82 @@ fn make_parser<'a, 'b>() -> App<'a, 'b> {
                 .long_help("Region of AWS to use. Defaults to the Global Endpoint")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("exec_credential_version")
+                .long("--exec-credential-version")
+                .help("ExecCredential API Version (eks only)")
+                .long_help(
+                    "The `client.authentication.k8s.io` API version to emit the EKS \
+                     ExecCredential as. Use `v1alpha1` for older clusters. Has no effect on \
+                     the `gke` type, which authenticates via the client-go GCP auth provider's \
+                     own cache format rather than an ExecCredential.",
+                )
+                .takes_value(true)
+                .possible_values(aws::EXEC_CREDENTIAL_VERSION_VARIANTS)
+                .default_value("v1beta1"),
+        )
+        .arg(
+            Arg::with_name("gcp_workload_audience")
+                .long("--gcp-workload-audience")
+                .help("GCP Workload Identity Pool Audience")
+                .long_help(
+                    "STS audience identifying the workload identity pool/provider to exchange \
+                     the subject token with. Setting this switches `gcp` to workload identity \
+                     federation instead of the Google SDK authentication flow",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gcp_subject_token_file")
+                .long("--gcp-subject-token-file")
+                .help("GCP Subject Token File")
+                .long_help(
+                    "Path to a file containing the external subject token, e.g. a projected \
+                     Kubernetes service account token",
+                )
+                .takes_value(true)
+                .conflicts_with("gcp_subject_token_url"),
+        )
+        .arg(
+            Arg::with_name("gcp_subject_token_url")
+                .long("--gcp-subject-token-url")
+                .help("GCP Subject Token URL")
+                .long_help("URL to fetch the external subject token from")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gcp_subject_token_type")
+                .long("--gcp-subject-token-type")
+                .help("GCP Subject Token Type")
+                .long_help(
+                    "The `subject_token_type` to present to the STS token endpoint. \
+                     Defaults to an OIDC JWT",
+                )
+                .takes_value(true)
+                .default_value(gcp::SUBJECT_TOKEN_TYPE_JWT),
+        )
+        .arg(
+            Arg::with_name("gcp_service_account_impersonation_url")
+                .long("--gcp-service-account-impersonation-url")
+                .help("GCP Service Account Impersonation URL")
+                .long_help(
+                    "IAM Credentials API URL to impersonate a target service account with the \
+                     federated access token. Omit to use the federated access token directly",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("gcp_scopes")
+                .long("--gcp-scopes")
+                .help("GCP OAuth Scopes")
+                .long_help(
+                    "Comma-separated list of OAuth scopes to request with workload identity \
+                     federation. The Google SDK authentication flow always requests \
+                     `https://www.googleapis.com/auth/cloud-platform`",
+                )
+                .takes_value(true)
+                .default_value("https://www.googleapis.com/auth/cloud-platform"),
+        )
         .arg(
             Arg::with_name("type")
                 .help("Credentials Type")
@@ -146,13 +383,42 @@ fn make_parser<'a, 'b>() -> App<'a, 'b> {
                 .index(1)
                 .required(true),
         )
+        .arg(
+            Arg::with_name("no_cache")
+                .long("--no-cache")
+                .help("Disable the on-disk credential cache")
+                .long_help(
+                    "Always fetch a fresh credential from Vault instead of reusing a cached one.",
+                ),
+        )
+        .arg(
+            Arg::with_name("cache_dir")
+                .long("--cache-dir")
+                .help("Credential Cache Directory")
+                .long_help(
+                    "Directory to store cached credentials in. \
+                     Defaults to `~/.vault-k8s-helper/cache`",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("cache_safety_margin")
+                .long("--cache-safety-margin")
+                .help("Cache Safety Margin")
+                .long_help(
+                    "How many seconds before a cached credential's real expiry it is treated \
+                     as stale and refetched.",
+                )
+                .takes_value(true)
+                .default_value("60"),
+        )
         .arg(
             Arg::with_name("path")
                 .help("Vault Path")
                 .long_help("Path to read from Vault")
                 .takes_value(true)
                 .index(2)
-                .required_ifs(&[("type", "gke"), ("type", "eks")]),
+                .required_ifs(&[("type", "gke"), ("type", "eks"), ("type", "aws-process")]),
         )
 }
 
@@ -207,28 +473,52 @@ fn get_vault_client(args: &ArgMatches<'_>) -> Result<Client, Error> {
     Ok(client)
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    env_logger::init();
-    let parser = make_parser();
-    let args = parser.get_matches();
+async fn run_docker_credential_helper(args: &ArgMatches<'_>) -> Result<(), Error> {
+    let registry_map_entries: Vec<&str> = args
+        .values_of("registry_map")
+        .map(|values| values.collect())
+        .unwrap_or_default();
+    let registry_map = docker::parse_registry_map(registry_map_entries.into_iter())?;
 
-    let credential_type: CredentialType =
-        CredentialType::from_str(required_arg_value(&args, "type"))
-            .expect("invalid values to be validated by clap");
-    let output = required_arg_value(&args, "output");
+    match args.subcommand_name() {
+        Some("get") => {
+            let hostname = docker::read_hostname_from_stdin()?;
+            let mapping = registry_map
+                .get(&hostname)
+                .ok_or_else(|| Error::UnknownRegistry(hostname.clone()))?;
+            let client = get_vault_client(args)?;
+            let credential = docker::get(&client, mapping, &hostname).await?;
+            println!("{}", serde_json::to_string(&credential)?);
+            Ok(())
+        }
+        // `store`/`erase` are no-ops: credentials are always sourced fresh
+        // from Vault on `get`, there is nothing to persist or remove.
+        Some("store") | Some("erase") => Ok(()),
+        _ => Err(Error::InvalidCredentialType),
+    }
+}
 
-    let creds = match credential_type {
+/// Fetches a fresh credential (from Vault for `gke`/`eks`/`aws-process`; for
+/// `gcp`, from the Google SDK or workload identity federation), returning its
+/// serialized output alongside its expiry, if any.
+async fn fetch_credential(
+    args: &ArgMatches<'_>,
+    credential_type: &CredentialType,
+) -> Result<(String, Option<DateTime<Utc>>), Error> {
+    Ok(match credential_type {
         CredentialType::Gke => {
-            let client = get_vault_client(&args)?;
-            let path = required_arg_value(&args, "path");
+            let client = get_vault_client(args)?;
+            let path = required_arg_value(args, "path");
             info!("Requesting GKE Access token from {}", path);
             let gcp_access_token = gcp::read_gcp_access_token(&client, path).await?;
-            serde_json::to_string_pretty(&gcp_access_token)?
+            let expiry = DateTime::parse_from_rfc3339(&gcp_access_token.expiry)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc));
+            (serde_json::to_string_pretty(&gcp_access_token)?, expiry)
         }
         CredentialType::Eks => {
-            let client = get_vault_client(&args)?;
-            let path = required_arg_value(&args, "path");
+            let client = get_vault_client(args)?;
+            let path = required_arg_value(args, "path");
             info!("Requesting AWS Credentials from {}", path);
             let request = vault::secrets::aws::CredentialsRequest {
                 role_arn: args.value_of("eks_role_arn").map(|s| s.to_string()),
@@ -236,22 +526,175 @@ async fn main() -> Result<(), Error> {
             };
             let aws_credentials = aws::read_aws_credentials(&client, path, &request).await?;
             debug!("AWS Credentials: {:#?}", aws_credentials);
+            let expiry = aws_credentials.expires_at().cloned();
+            let exec_credential_version = aws::ExecCredentialVersion::from_str(required_arg_value(
+                args,
+                "exec_credential_version",
+            ))
+            .expect("invalid values to be validated by clap");
             let token = aws::get_eks_token(
                 &aws_credentials,
-                required_arg_value(&args, "eks_cluster"),
+                required_arg_value(args, "eks_cluster"),
                 args.value_of("eks_region"),
                 args.value_of("eks_expiry"),
+                exec_credential_version,
             )?;
-            serde_json::to_string_pretty(&token)?
+            (serde_json::to_string_pretty(&token)?, expiry)
+        }
+        CredentialType::AwsProcess => {
+            let client = get_vault_client(args)?;
+            let path = required_arg_value(args, "path");
+            info!("Requesting AWS Credentials from {}", path);
+            let request = vault::secrets::aws::CredentialsRequest {
+                role_arn: args.value_of("eks_role_arn").map(|s| s.to_string()),
+                ttl: args.value_of("eks_ttl").map(|s| s.to_string()),
+            };
+            let aws_credentials = aws::read_aws_credentials(&client, path, &request).await?;
+            debug!("AWS Credentials: {:#?}", aws_credentials);
+            let expiry = aws_credentials.expires_at().cloned();
+            let output = aws::get_credential_process_output(&aws_credentials);
+            (serde_json::to_string_pretty(&output)?, expiry)
         }
         CredentialType::Gcp => {
-            info!("Using Google SDK authentication flow");
-            let auth = gcp_auth::init().await?;
-            let token = auth
-                .get_token(&["https://www.googleapis.com/auth/cloud-platform"])
-                .await?;
-            serde_json::to_string_pretty(&gcp::GcpAccessToken::from_gcp_auth(&token))?
+            if let Some(audience) = args.value_of("gcp_workload_audience") {
+                info!("Using GCP workload identity federation");
+                let subject_token = if let Some(path) = args.value_of("gcp_subject_token_file") {
+                    read_token(path)?
+                } else if let Some(url) = args.value_of("gcp_subject_token_url") {
+                    gcp::fetch_subject_token(url).await?
+                } else {
+                    return Err(Error::MissingSubjectToken);
+                };
+                let scopes: Vec<&str> = required_arg_value(args, "gcp_scopes").split(',').collect();
+                let config = gcp::ExternalAccountConfig {
+                    audience,
+                    subject_token: &subject_token,
+                    subject_token_type: required_arg_value(args, "gcp_subject_token_type"),
+                    scopes,
+                    service_account_impersonation_url: args
+                        .value_of("gcp_service_account_impersonation_url"),
+                };
+                let gcp_access_token = gcp::read_workload_identity_token(&config).await?;
+                let expiry = DateTime::parse_from_rfc3339(&gcp_access_token.expiry)
+                    .ok()
+                    .map(|dt| dt.with_timezone(&Utc));
+                (serde_json::to_string_pretty(&gcp_access_token)?, expiry)
+            } else {
+                info!("Using Google SDK authentication flow");
+                let auth = gcp_auth::init().await?;
+                let token = auth
+                    .get_token(&["https://www.googleapis.com/auth/cloud-platform"])
+                    .await?;
+                let expiry = token.expires_at();
+                (
+                    serde_json::to_string_pretty(&gcp::GcpAccessToken::from_gcp_auth(&token))?,
+                    expiry,
+                )
+            }
+        }
+    })
+}
+
+/// Builds the cache key identifier for `credential_type`, folding in every
+/// argument that distinguishes one request for that type from another (not
+/// just the Vault path), so two invocations that would produce different
+/// credentials never share a cache entry.
+fn cache_identifier(args: &ArgMatches<'_>, credential_type: &CredentialType) -> String {
+    match credential_type {
+        CredentialType::Gke => args.value_of("path").unwrap_or_default().to_string(),
+        CredentialType::Eks => [
+            args.value_of("path").unwrap_or_default(),
+            args.value_of("eks_role_arn").unwrap_or_default(),
+            args.value_of("eks_ttl").unwrap_or_default(),
+            args.value_of("eks_region").unwrap_or_default(),
+            args.value_of("eks_cluster").unwrap_or_default(),
+            args.value_of("eks_expiry").unwrap_or_default(),
+            args.value_of("exec_credential_version").unwrap_or_default(),
+        ]
+        .join("|"),
+        CredentialType::AwsProcess => [
+            args.value_of("path").unwrap_or_default(),
+            args.value_of("eks_role_arn").unwrap_or_default(),
+            args.value_of("eks_ttl").unwrap_or_default(),
+        ]
+        .join("|"),
+        CredentialType::Gcp => match args.value_of("gcp_workload_audience") {
+            Some(audience) => [
+                audience,
+                args.value_of("gcp_subject_token_file").unwrap_or_default(),
+                args.value_of("gcp_subject_token_url").unwrap_or_default(),
+                args.value_of("gcp_subject_token_type").unwrap_or_default(),
+                args.value_of("gcp_service_account_impersonation_url")
+                    .unwrap_or_default(),
+                args.value_of("gcp_scopes").unwrap_or_default(),
+            ]
+            .join("|"),
+            None => "ambient".to_string(),
+        },
+    }
+}
+
+async fn run_serve(args: &ArgMatches<'_>) -> Result<(), Error> {
+    let type_str = required_arg_value(args, "type");
+    let credential_type: CredentialType =
+        CredentialType::from_str(type_str).expect("invalid values to be validated by clap");
+    let refresh_margin: f64 = required_arg_value(args, "refresh_margin")
+        .parse()
+        .map_err(|_| Error::InvalidRefreshMargin)?;
+    if !(0.0..=1.0).contains(&refresh_margin) {
+        return Err(Error::InvalidRefreshMargin);
+    }
+    let socket_path = args
+        .value_of("socket_path")
+        .map(PathBuf::from)
+        .unwrap_or_else(serve::default_socket_path);
+
+    serve::serve(args, &credential_type, refresh_margin, &socket_path).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    env_logger::init();
+    let parser = make_parser();
+    let args = parser.get_matches();
+
+    if let Some(docker_args) = args.subcommand_matches("docker") {
+        return run_docker_credential_helper(docker_args).await;
+    }
+    if let Some(serve_args) = args.subcommand_matches("serve") {
+        return run_serve(serve_args).await;
+    }
+
+    let type_str = required_arg_value(&args, "type");
+    let credential_type: CredentialType =
+        CredentialType::from_str(type_str).expect("invalid values to be validated by clap");
+    let output = required_arg_value(&args, "output");
+
+    let cache_dir = if args.is_present("no_cache") {
+        None
+    } else {
+        args.value_of("cache_dir")
+            .map(PathBuf::from)
+            .or_else(cache::default_cache_dir)
+    };
+    let cache_safety_margin: i64 = required_arg_value(&args, "cache_safety_margin")
+        .parse()
+        .map_err(|_| Error::InvalidCacheSafetyMargin)?;
+    let cache_identifier = cache_identifier(&args, &credential_type);
+    let cached = cache_dir
+        .as_ref()
+        .and_then(|dir| cache::read_cached(dir, type_str, &cache_identifier, cache_safety_margin));
+
+    let creds = if let Some(cached) = cached {
+        cached
+    } else {
+        let (creds, expiry) = fetch_credential(&args, &credential_type).await?;
+
+        if let (Some(dir), Some(expiry)) = (&cache_dir, expiry) {
+            cache::write_cache(dir, type_str, &cache_identifier, &creds, expiry)?;
         }
+
+        creds
     };
 
     let output = get_writer(output)?;
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use clap::ArgMatches;
+use log::{debug, info, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixListener;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{fetch_credential, CredentialType, Error};
+
+/// Fallback delay used when a credential has no known expiry (e.g. static,
+/// non-STS AWS keys).
+const DEFAULT_REFRESH_DELAY: Duration = Duration::from_secs(300);
+
+/// Floor under which we never schedule a refresh, so an expired (or
+/// already-past) credential doesn't turn into a tight spin against Vault.
+const MIN_REFRESH_DELAY: Duration = Duration::from_secs(5);
+
+/// Cap on the exponential backoff applied after consecutive failed
+/// refreshes.
+const MAX_BACKOFF_DELAY: Duration = Duration::from_secs(300);
+
+/// Binds the Unix domain socket with a restrictive umask held for the
+/// duration of the call, so the socket never exists with the default
+/// (umask-derived) permissions even momentarily: the socket hands out a
+/// live Vault-issued credential to whoever can connect to it.
+fn bind_private_socket(socket_path: &Path) -> Result<UnixListener, Error> {
+    // SAFETY: `umask` is process-global; we hold it just long enough to
+    // cover the `bind` call and restore the previous value immediately
+    // after, same as a lock.
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let result = UnixListener::bind(socket_path);
+    unsafe { libc::umask(previous_umask) };
+    Ok(result?)
+}
+
+pub fn default_socket_path() -> PathBuf {
+    dirs::home_dir()
+        .map(|mut p| {
+            p.push(".vault-k8s-helper");
+            p.push("serve.sock");
+            p
+        })
+        .unwrap_or_else(|| PathBuf::from("/tmp/vault-k8s-helper-serve.sock"))
+}
+
+fn refresh_delay(expiry: Option<DateTime<Utc>>, refresh_margin: f64) -> Duration {
+    let expiry = match expiry {
+        Some(expiry) => expiry,
+        None => return DEFAULT_REFRESH_DELAY,
+    };
+    let ttl_secs = (expiry - Utc::now()).num_seconds().max(0) as f64;
+    Duration::from_secs_f64((ttl_secs * refresh_margin).max(0.0)).max(MIN_REFRESH_DELAY)
+}
+
+/// Backoff to apply after `consecutive_failures` failed refreshes in a row,
+/// so a persistent Vault outage doesn't turn into a tight retry loop.
+fn backoff_delay(consecutive_failures: u32) -> Duration {
+    MIN_REFRESH_DELAY
+        .saturating_mul(1u32 << consecutive_failures.min(6))
+        .min(MAX_BACKOFF_DELAY)
+}
+
+/// Keeps the Vault-issued credential for `credential_type` fresh, proactively
+/// refreshing it once `refresh_margin` of its remaining TTL has elapsed, and
+/// serves the latest output to clients connecting on `socket_path`.
+pub async fn serve(
+    args: &ArgMatches<'_>,
+    credential_type: &CredentialType,
+    refresh_margin: f64,
+    socket_path: &Path,
+) -> Result<(), Error> {
+    let (mut output, mut expiry) = fetch_credential(args, credential_type).await?;
+
+    if let Some(parent) = socket_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let _ = std::fs::remove_file(socket_path);
+    let listener = bind_private_socket(socket_path)?;
+    info!("Serving credential on {}", socket_path.display());
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut consecutive_failures = 0u32;
+    let mut delay = refresh_delay(expiry, refresh_margin);
+    debug!("Next credential refresh in {:?}", delay);
+    let mut refresh_at = tokio::time::Instant::now() + delay;
+
+    let result = loop {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => {
+                break result.map_err(Error::from);
+            }
+            _ = sigterm.recv() => {
+                break Ok(());
+            }
+            _ = tokio::time::sleep_until(refresh_at) => {
+                match fetch_credential(args, credential_type).await {
+                    Ok((new_output, new_expiry)) => {
+                        info!("Refreshed credential");
+                        output = new_output;
+                        expiry = new_expiry;
+                        consecutive_failures = 0;
+                    }
+                    Err(e) => {
+                        consecutive_failures += 1;
+                        warn!("Failed to refresh credential, keeping existing one: {}", e);
+                    }
+                }
+                delay = refresh_delay(expiry, refresh_margin)
+                    .max(backoff_delay(consecutive_failures));
+                debug!("Next credential refresh in {:?}", delay);
+                refresh_at = tokio::time::Instant::now() + delay;
+            }
+            accepted = listener.accept() => {
+                let (mut stream, _) = accepted?;
+                let output = output.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = stream.write_all(output.as_bytes()).await {
+                        warn!("Failed to write credential to client: {}", e);
+                    }
+                });
+            }
+        }
+    };
+
+    let _ = std::fs::remove_file(socket_path);
+    result
+}